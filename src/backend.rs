@@ -0,0 +1,16 @@
+use clap::ValueEnum;
+
+mod symphonia_backend;
+
+/// Which audio pipeline performs the downmix, selected via `--backend`.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum BackendKind {
+    /// Shell out to ffmpeg for both decode and encode (the default).
+    #[default]
+    Ffmpeg,
+    /// Decode and downmix in-process with symphonia, then mux the result with ffmpeg. Removes
+    /// the hard runtime dependency on ffmpeg for the audio path.
+    Symphonia,
+}
+
+pub use symphonia_backend::run as run_symphonia;
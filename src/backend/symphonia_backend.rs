@@ -0,0 +1,189 @@
+use std::{fs::File, path::Path, process::Command};
+
+use anyhow::{ensure, Context};
+use symphonia::core::{
+    audio::{AudioBufferRef, Signal},
+    codecs::DecoderOptions,
+    errors::Error as SymphoniaError,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use crate::matrix::{self, MatrixPreset};
+
+/// Decodes `input_path`'s audio in-process with symphonia, downmixes it to stereo with
+/// `preset`, and muxes the result alongside a stream-copied video track into `output_path`.
+/// When `normalize` is set, the folded stereo signal is peak-limited so it doesn't clip; unlike
+/// the ffmpeg backend's `loudnorm` filter, this does not normalize loudness.
+pub fn run(
+    ffmpeg_bin: &Path,
+    input_path: &Path,
+    output_path: &Path,
+    preset: MatrixPreset,
+    channel_layout: &str,
+    force: bool,
+    normalize: bool,
+) -> anyhow::Result<()> {
+    let decoded = decode_channels(input_path)?;
+    let mut stereo = matrix::downmix_to_stereo(preset, channel_layout, &decoded.channels)?;
+
+    if normalize {
+        limit_peak(&mut stereo);
+    }
+
+    mux_with_video(
+        ffmpeg_bin,
+        input_path,
+        output_path,
+        &stereo,
+        decoded.sample_rate,
+        force,
+    )
+}
+
+/// Scales `samples` down so their peak absolute value is at most 1.0, leaving them untouched
+/// if they don't clip. A simple peak limiter, not full EBU R128 loudness normalization.
+fn limit_peak(samples: &mut [f32]) {
+    let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+
+    if peak > 1.0 {
+        for sample in samples {
+            *sample /= peak;
+        }
+    }
+}
+
+/// Deinterleaved audio decoded from the source file's default audio track.
+struct DecodedAudio {
+    sample_rate: u32,
+    channels: Vec<Vec<f32>>,
+}
+
+fn decode_channels(input_path: &Path) -> anyhow::Result<DecodedAudio> {
+    let file = File::open(input_path)
+        .with_context(|| format!("failed to open '{}'", input_path.display()))?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = input_path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        source,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.channels.is_some())
+        .context("no decodable audio track found")?
+        .clone();
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())?;
+
+    let sample_rate = track.codec_params.sample_rate.context("unknown sample rate")?;
+    let channel_count = track
+        .codec_params
+        .channels
+        .context("unknown channel count")?
+        .count();
+
+    let mut channels: Vec<Vec<f32>> = vec![Vec::new(); channel_count];
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(_) | SymphoniaError::ResetRequired) => break,
+            Err(err) => return Err(err.into()),
+        };
+
+        if packet.track_id() != track.id {
+            continue;
+        }
+
+        match decoder.decode(&packet)? {
+            AudioBufferRef::F32(buf) => {
+                for (channel, samples) in channels.iter_mut().zip(buf.planes().planes()) {
+                    channel.extend_from_slice(samples);
+                }
+            }
+            other => {
+                let mut buf = other.make_equivalent::<f32>();
+                other.convert(&mut buf);
+                for (channel, samples) in channels.iter_mut().zip(buf.planes().planes()) {
+                    channel.extend_from_slice(samples);
+                }
+            }
+        }
+    }
+
+    Ok(DecodedAudio {
+        sample_rate,
+        channels,
+    })
+}
+
+/// Writes `stereo` to a temporary raw PCM file and asks ffmpeg to mux it with the original
+/// file's video track (stream-copied). ffmpeg does not decode or mix the source audio; it only
+/// encodes the stereo PCM with the output container's default audio codec.
+fn mux_with_video(
+    ffmpeg_bin: &Path,
+    input_path: &Path,
+    output_path: &Path,
+    stereo: &[f32],
+    sample_rate: u32,
+    force: bool,
+) -> anyhow::Result<()> {
+    let pcm_path = output_path.with_extension("downmix.pcm");
+    let pcm_bytes: Vec<u8> = stereo.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+    std::fs::write(&pcm_path, &pcm_bytes)
+        .with_context(|| format!("failed to write temporary PCM to '{}'", pcm_path.display()))?;
+
+    let result = (|| -> anyhow::Result<()> {
+        let mut ffmpeg_args = vec![
+            "-i".to_owned(),
+            input_path.display().to_string(),
+            "-f".to_owned(),
+            "f32le".to_owned(),
+            "-ar".to_owned(),
+            sample_rate.to_string(),
+            "-ac".to_owned(),
+            "2".to_owned(),
+            "-i".to_owned(),
+            pcm_path.display().to_string(),
+            "-map".to_owned(),
+            "0:v".to_owned(),
+            "-map".to_owned(),
+            "1:a".to_owned(),
+            "-c:v".to_owned(),
+            "copy".to_owned(),
+        ];
+        if force {
+            ffmpeg_args.push("-y".to_owned());
+        }
+        ffmpeg_args.push(output_path.display().to_string());
+
+        let output = Command::new(ffmpeg_bin).args(&ffmpeg_args).output()?;
+
+        ensure!(
+            output.status.success(),
+            "ffmpeg exited with {}:\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Ok(())
+    })();
+
+    let _ = std::fs::remove_file(&pcm_path);
+
+    result
+}
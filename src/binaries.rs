@@ -0,0 +1,96 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, ensure, Context};
+
+mod download;
+
+/// Resolved paths to the `ffmpeg` and `ffprobe` binaries this tool shells out to.
+#[derive(Debug, Clone)]
+pub struct Binaries {
+    pub ffmpeg: PathBuf,
+    pub ffprobe: PathBuf,
+}
+
+/// Resolves both binaries, checking in order: the `--ffmpeg-path`/`--ffprobe-path` flags, the
+/// `FFMPEG`/`FFPROBE` env vars, `PATH`, and finally — if `download` is set — a static build
+/// fetched into a cache directory on demand.
+pub fn resolve(
+    ffmpeg_path: Option<&Path>,
+    ffprobe_path: Option<&Path>,
+    download: bool,
+) -> anyhow::Result<Binaries> {
+    Ok(Binaries {
+        ffmpeg: resolve_one("ffmpeg", ffmpeg_path, "FFMPEG", download)?,
+        ffprobe: resolve_one("ffprobe", ffprobe_path, "FFPROBE", download)?,
+    })
+}
+
+fn resolve_one(
+    name: &str,
+    explicit: Option<&Path>,
+    env_var: &str,
+    download: bool,
+) -> anyhow::Result<PathBuf> {
+    if let Some(path) = explicit {
+        return verify(path.to_owned(), name);
+    }
+
+    if let Some(path) = env::var_os(env_var) {
+        return verify(PathBuf::from(path), name);
+    }
+
+    if let Some(path) = find_on_path(name) {
+        return verify(path, name);
+    }
+
+    if download {
+        return download::fetch(name);
+    }
+
+    bail!(
+        "could not find '{name}'. Install it and put it on PATH, pass --{name}-path, set ${env_var}, \
+         or pass --download to fetch a static build"
+    )
+}
+
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let exe_name = exe_name(name);
+
+    env::var_os("PATH").and_then(|path_var| {
+        env::split_paths(&path_var).find_map(|dir| {
+            let candidate = dir.join(&exe_name);
+            candidate.is_file().then_some(candidate)
+        })
+    })
+}
+
+fn exe_name(name: &str) -> String {
+    if cfg!(windows) {
+        format!("{name}.exe")
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Confirms `path` is actually runnable, so a stale or misconfigured path fails with a clear
+/// error instead of a raw `NotFound` further down the line.
+fn verify(path: PathBuf, name: &str) -> anyhow::Result<PathBuf> {
+    let status = Command::new(&path)
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("failed to run '{}' as {name}", path.display()))?;
+
+    ensure!(
+        status.success(),
+        "'{}' exited with {status} when run as `{name} -version`",
+        path.display()
+    );
+
+    Ok(path)
+}
@@ -0,0 +1,206 @@
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+/// Fetches a static ffmpeg build for the current platform into a cache directory and returns
+/// the path to `name` (`ffmpeg` or `ffprobe`) within it, mirroring ffmpeg-sidecar's download
+/// module: detect platform, fetch the matching package, unpack, and verify the binary runs.
+pub fn fetch(name: &str) -> anyhow::Result<PathBuf> {
+    let cache_dir = cache_dir()?;
+    let binary_path = cache_dir.join(super::exe_name(name));
+
+    if binary_path.is_file() {
+        return super::verify(binary_path, name);
+    }
+
+    fs::create_dir_all(&cache_dir)
+        .with_context(|| format!("failed to create cache dir '{}'", cache_dir.display()))?;
+
+    let (url, format) = package()?;
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("failed to download static ffmpeg build from '{url}'"))?;
+
+    let mut archive = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut archive)
+        .context("failed to read downloaded archive")?;
+
+    unpack(&archive, format, &cache_dir)?;
+
+    anyhow::ensure!(
+        binary_path.is_file(),
+        "downloaded archive from '{url}' did not contain '{name}'"
+    );
+
+    super::verify(binary_path, name)
+}
+
+fn cache_dir() -> anyhow::Result<PathBuf> {
+    let base = dirs::cache_dir().context("could not determine a cache directory for this platform")?;
+    Ok(base.join("downmix").join("ffmpeg"))
+}
+
+/// The archive format a static build ships in, so `unpack` can dispatch on platform rather
+/// than guessing from the URL (some hosts, e.g. evermeet.cx, serve zips from extensionless
+/// URLs).
+#[derive(Clone, Copy)]
+enum ArchiveFormat {
+    TarXz,
+    Zip,
+}
+
+fn package() -> anyhow::Result<(&'static str, ArchiveFormat)> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Ok((
+            "https://johnvansickle.com/ffmpeg/releases/ffmpeg-release-amd64-static.tar.xz",
+            ArchiveFormat::TarXz,
+        )),
+        ("linux", "aarch64") => Ok((
+            "https://johnvansickle.com/ffmpeg/releases/ffmpeg-release-arm64-static.tar.xz",
+            ArchiveFormat::TarXz,
+        )),
+        ("macos", _) => Ok(("https://evermeet.cx/ffmpeg/getrelease/zip", ArchiveFormat::Zip)),
+        ("windows", _) => Ok((
+            "https://www.gyan.dev/ffmpeg/builds/ffmpeg-release-essentials.zip",
+            ArchiveFormat::Zip,
+        )),
+        (os, arch) => anyhow::bail!("no static ffmpeg build is known for {os}/{arch}"),
+    }
+}
+
+/// Extracts the `ffmpeg`/`ffprobe` entries of `archive` into `dest`, flattening their paths so
+/// they land directly in `dest` regardless of the directory they ship in inside the package.
+/// Every other entry is skipped.
+fn unpack(archive: &[u8], format: ArchiveFormat, dest: &Path) -> anyhow::Result<()> {
+    match format {
+        ArchiveFormat::TarXz => {
+            let decompressed = xz2::read::XzDecoder::new(archive);
+            let mut tar = tar::Archive::new(decompressed);
+
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                let name = entry
+                    .path()?
+                    .file_name()
+                    .map(|name| name.to_os_string())
+                    .context("tar entry with no file name")?;
+
+                if matches!(name.to_str(), Some("ffmpeg" | "ffprobe")) {
+                    entry.unpack(dest.join(name))?;
+                }
+            }
+
+            Ok(())
+        }
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive))?;
+
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i)?;
+                let Some(name) = entry
+                    .enclosed_name()
+                    .and_then(|path| path.file_name().map(|n| n.to_os_string()))
+                else {
+                    continue;
+                };
+
+                if matches!(
+                    name.to_str(),
+                    Some("ffmpeg" | "ffprobe" | "ffmpeg.exe" | "ffprobe.exe")
+                ) {
+                    let mut out = fs::File::create(dest.join(name))?;
+                    std::io::copy(&mut entry, &mut out)?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// A scratch directory under the OS temp dir, removed when dropped.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("downmix-unpack-test-{name}"));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn tar_xz_archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *contents).unwrap();
+        }
+        let tar_bytes = builder.into_inner().unwrap();
+
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 0);
+        encoder.write_all(&tar_bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zip_archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn unpack_tar_xz_extracts_known_binaries_and_flattens_their_directory() {
+        let archive = tar_xz_archive(&[
+            ("ffmpeg-release-amd64-static/ffmpeg", b"fake ffmpeg"),
+            ("ffmpeg-release-amd64-static/ffprobe", b"fake ffprobe"),
+            ("ffmpeg-release-amd64-static/README.txt", b"docs"),
+        ]);
+        let dest = ScratchDir::new("tar-xz");
+
+        unpack(&archive, ArchiveFormat::TarXz, &dest.0).unwrap();
+
+        assert_eq!(fs::read(dest.0.join("ffmpeg")).unwrap(), b"fake ffmpeg");
+        assert_eq!(fs::read(dest.0.join("ffprobe")).unwrap(), b"fake ffprobe");
+        assert!(!dest.0.join("README.txt").exists());
+    }
+
+    #[test]
+    fn unpack_zip_extracts_known_binaries_and_flattens_their_directory() {
+        let archive = zip_archive(&[
+            ("ffmpeg-essentials/bin/ffmpeg.exe", b"fake ffmpeg"),
+            ("ffmpeg-essentials/bin/ffprobe.exe", b"fake ffprobe"),
+            ("ffmpeg-essentials/LICENSE", b"license text"),
+        ]);
+        let dest = ScratchDir::new("zip");
+
+        unpack(&archive, ArchiveFormat::Zip, &dest.0).unwrap();
+
+        assert_eq!(fs::read(dest.0.join("ffmpeg.exe")).unwrap(), b"fake ffmpeg");
+        assert_eq!(fs::read(dest.0.join("ffprobe.exe")).unwrap(), b"fake ffprobe");
+        assert!(!dest.0.join("LICENSE").exists());
+    }
+}
@@ -0,0 +1,152 @@
+use std::{path::Path, process::Command};
+
+use serde::Deserialize;
+
+/// Errors that can occur while probing a file's audio/video streams with ffprobe.
+#[derive(Debug, thiserror::Error)]
+pub enum AnalyzeError {
+    #[error("failed to run ffprobe on '{path}'")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("ffprobe exited with {status} analyzing '{path}':\n{stderr}")]
+    Probe {
+        path: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+
+    #[error("failed to parse ffprobe output for '{path}'")]
+    Parse {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// The top-level shape of `ffprobe -show_streams -show_format -print_format json`.
+#[derive(Debug, Deserialize)]
+pub struct FfprobeOutput {
+    pub streams: Vec<Stream>,
+    pub format: Format,
+}
+
+/// A single stream entry from ffprobe's output. Only the fields this tool cares about are kept.
+#[derive(Debug, Deserialize)]
+pub struct Stream {
+    pub codec_type: String,
+    pub channels: Option<i64>,
+    pub channel_layout: Option<String>,
+    pub codec_name: Option<String>,
+}
+
+/// The `format` section of ffprobe's output, giving container-level metadata.
+#[derive(Debug, Deserialize)]
+pub struct Format {
+    /// The container duration in seconds, serialized by ffprobe as a decimal string.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub duration: Option<f64>,
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.and_then(|duration| duration.parse().ok()))
+}
+
+impl FfprobeOutput {
+    /// Returns the audio streams, ignoring video/subtitle/data streams.
+    pub fn audio_streams(&self) -> impl Iterator<Item = &Stream> {
+        self.streams
+            .iter()
+            .filter(|stream| stream.codec_type == "audio")
+    }
+}
+
+/// Runs `ffprobe_bin` on `path` and deserializes its stream metadata.
+pub fn probe(ffprobe_bin: &Path, path: &Path) -> Result<FfprobeOutput, AnalyzeError> {
+    let path_str = path.display().to_string();
+
+    let ffprobe_args = [
+        path.to_str().unwrap_or(&path_str),
+        "-show_streams",
+        "-show_format",
+        "-loglevel",
+        "error",
+        "-print_format",
+        "json",
+    ];
+
+    let output = Command::new(ffprobe_bin)
+        .args(ffprobe_args)
+        .output()
+        .map_err(|source| AnalyzeError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+
+    if !output.status.success() {
+        return Err(AnalyzeError::Probe {
+            path: path_str,
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    parse(&output.stdout, &path_str)
+}
+
+/// Deserializes raw `ffprobe -print_format json` output, split out from [`probe`] so the
+/// parsing step can be tested without spawning a real ffprobe process.
+fn parse(json: &[u8], path_str: &str) -> Result<FfprobeOutput, AnalyzeError> {
+    serde_json::from_slice(json).map_err(|source| AnalyzeError::Parse {
+        path: path_str.to_owned(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_channels_and_duration() {
+        let json = br#"{
+            "streams": [
+                {"codec_type": "audio", "channels": 6, "channel_layout": "5.1", "codec_name": "aac"}
+            ],
+            "format": {"duration": "123.456000"}
+        }"#;
+
+        let probed = parse(json, "input.mkv").unwrap();
+        let stream = probed.audio_streams().next().unwrap();
+
+        assert_eq!(stream.channels, Some(6));
+        assert_eq!(stream.channel_layout.as_deref(), Some("5.1"));
+        assert_eq!(probed.format.duration, Some(123.456));
+    }
+
+    #[test]
+    fn parse_fails_on_malformed_json() {
+        let err = parse(b"not json", "input.mkv").unwrap_err();
+        assert!(matches!(err, AnalyzeError::Parse { path, .. } if path == "input.mkv"));
+    }
+
+    #[test]
+    fn audio_streams_ignores_video_streams() {
+        let json = br#"{
+            "streams": [
+                {"codec_type": "video", "channels": null, "channel_layout": null, "codec_name": "h264"},
+                {"codec_type": "audio", "channels": 2, "channel_layout": "stereo", "codec_name": "aac"}
+            ],
+            "format": {}
+        }"#;
+
+        let probed = parse(json, "input.mkv").unwrap();
+        assert_eq!(probed.audio_streams().count(), 1);
+    }
+}
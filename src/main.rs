@@ -1,13 +1,35 @@
 use anyhow::{ensure, Context};
 use clap::Parser;
-use std::{path::PathBuf, process::Command};
-use tracing::{info, Level};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+use tracing::{error, info, warn, Level};
+
+use backend::BackendKind;
+use binaries::Binaries;
+use matrix::MatrixPreset;
+
+mod backend;
+mod binaries;
+mod ffprobe;
+mod matrix;
+mod progress;
+
+/// Video file extensions considered when walking a directory.
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "mov", "avi", "webm", "m4v", "ts", "flv", "wmv",
+];
 
 #[derive(Parser)]
 /// Downmixes a video file's audio into stereo sound if it isn't already
 struct Args {
+    /// A single video file, or a directory to recursively downmix
     input_path: PathBuf,
 
+    /// Output path for a single file, or the output root when `input_path` is a directory
     output_path: PathBuf,
 
     #[arg(short, long)]
@@ -17,6 +39,67 @@ struct Args {
     #[arg(short, long)]
     /// Overwrite an existing file
     force: bool,
+
+    #[arg(short, long, default_value_t = 1)]
+    /// Number of files to downmix concurrently when `input_path` is a directory
+    jobs: usize,
+
+    #[arg(short, long, value_enum)]
+    /// Downmix coefficient preset to use instead of ffmpeg's default stereo mix. If omitted, the
+    /// symphonia backend still applies the Itu preset by default, unlike the ffmpeg backend's
+    /// default plain -ac 2 mix -- pass this explicitly for matching output across backends.
+    matrix: Option<MatrixPreset>,
+
+    #[arg(short, long)]
+    /// Loudness-normalize the downmixed output so folded channels don't clip
+    normalize: bool,
+
+    #[arg(short, long, value_enum)]
+    /// Audio pipeline used to perform the downmix (defaults to ffmpeg). Note the two backends
+    /// have different defaults when --matrix is omitted; see --matrix.
+    backend: Option<BackendKind>,
+
+    #[arg(long)]
+    /// Path to the ffmpeg binary (defaults to $FFMPEG, then searching PATH)
+    ffmpeg_path: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Path to the ffprobe binary (defaults to $FFPROBE, then searching PATH)
+    ffprobe_path: Option<PathBuf>,
+
+    #[arg(short, long)]
+    /// Fetch a static ffmpeg/ffprobe build into a cache directory if not otherwise found
+    download: bool,
+}
+
+/// Per-file downmix settings, threaded through single-file and directory processing alike.
+#[derive(Clone)]
+struct DownmixOptions {
+    force: bool,
+    quiet: bool,
+    matrix: Option<MatrixPreset>,
+    normalize: bool,
+    backend: BackendKind,
+    binaries: Binaries,
+}
+
+impl DownmixOptions {
+    fn new(args: &Args) -> anyhow::Result<Self> {
+        let binaries = binaries::resolve(
+            args.ffmpeg_path.as_deref(),
+            args.ffprobe_path.as_deref(),
+            args.download,
+        )?;
+
+        Ok(Self {
+            force: args.force,
+            quiet: args.quiet,
+            matrix: args.matrix,
+            normalize: args.normalize,
+            backend: args.backend.unwrap_or_default(),
+            binaries,
+        })
+    }
 }
 
 fn main() -> anyhow::Result<()> {
@@ -35,103 +118,280 @@ fn main() -> anyhow::Result<()> {
         args.input_path.display(),
     );
 
-    ensure!(
-        args.input_path.is_file(),
-        "'{}' is not a file",
-        args.input_path.display(),
-    );
-
-    if !args.force {
+    if args.input_path.is_dir() {
+        run_directory(&args)
+    } else {
         ensure!(
-            !args.output_path.try_exists()?,
-            "'{}' already exists. Use --force to overwrite.",
-            args.output_path.display()
+            args.input_path.is_file(),
+            "'{}' is not a file",
+            args.input_path.display(),
         );
+
+        if !args.force {
+            ensure!(
+                !args.output_path.try_exists()?,
+                "'{}' already exists. Use --force to overwrite.",
+                args.output_path.display()
+            );
+        }
+
+        process_file(&args.input_path, &args.output_path, &DownmixOptions::new(&args)?)
+    }
+}
+
+/// Recursively discovers video files beneath `dir`, in no particular order.
+fn discover_video_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = VecDeque::from([dir.to_path_buf()]);
+
+    while let Some(current) = pending.pop_front() {
+        for entry in std::fs::read_dir(&current)
+            .with_context(|| format!("failed to read directory '{}'", current.display()))?
+        {
+            let entry = entry?;
+            let path = entry.path();
+
+            if path.is_dir() {
+                pending.push_back(path);
+            } else if path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| VIDEO_EXTENSIONS.iter().any(|video| video.eq_ignore_ascii_case(ext)))
+            {
+                files.push(path);
+            }
+        }
     }
 
-    let ffprobe_args = [
-        args.input_path
-            .to_str()
-            .context(format!("invalid path '{}'", args.input_path.display()))?,
-        "-show_streams",
-        "-loglevel",
-        "error",
-        "-print_format",
-        "json",
-    ];
+    Ok(files)
+}
 
-    let output = Command::new("ffprobe").args(ffprobe_args).output()?;
+/// Downmixes every video file discovered beneath `args.input_path`, mirroring the source tree
+/// structure beneath `args.output_path`, using a bounded pool of `args.jobs` worker threads.
+fn run_directory(args: &Args) -> anyhow::Result<()> {
+    let files = discover_video_files(&args.input_path)?;
+    let file_count = files.len();
 
-    ensure!(
-        output.stderr.is_empty(),
-        "Error from ffprobe:\n{}",
-        String::from_utf8_lossy(&output.stderr)
+    info!(
+        "Found {file_count} video file(s) beneath '{}'",
+        args.input_path.display()
     );
 
-    let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
-    let streams = json
-        .get("streams")
-        .and_then(|streams| streams.as_array())
-        .context("invalid json")?;
+    let jobs = args.jobs.max(1);
+    let work = Arc::new(Mutex::new(VecDeque::from(files)));
+    let (tx, rx) = mpsc::channel();
+    let mut options = DownmixOptions::new(args)?;
 
-    let mut too_many_channels = false;
-    for stream in streams {
-        if let Some(channels) = stream.get("channels") {
-            let channels = channels.as_i64().context("invalid metadata value")?;
+    // Each worker renders its own `\r`-prefixed progress bar to the same shared stderr; with
+    // more than one job those bars race each other into unreadable interleaved output, so
+    // suppress them automatically instead of garbling the terminal.
+    if jobs > 1 {
+        options.quiet = true;
+    }
 
-            info!(
-                "Found {channels} channels for '{}'",
-                args.input_path.display()
-            );
+    let failures = thread::scope(|scope| {
+        for _ in 0..jobs {
+            let work = Arc::clone(&work);
+            let tx = tx.clone();
+            let input_root = args.input_path.as_path();
+            let output_root = args.output_path.as_path();
+            let options = options.clone();
 
-            too_many_channels |= channels > 2;
+            scope.spawn(move || loop {
+                let input_path = {
+                    let mut work = work.lock().unwrap();
+                    match work.pop_front() {
+                        Some(path) => path,
+                        None => break,
+                    }
+                };
+
+                let relative = input_path.strip_prefix(input_root).unwrap_or(&input_path);
+                let output_path = output_root.join(relative);
+
+                let result = (|| -> anyhow::Result<()> {
+                    if let Some(parent) = output_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+
+                    if !options.force && output_path.try_exists()? {
+                        anyhow::bail!(
+                            "'{}' already exists. Use --force to overwrite.",
+                            output_path.display()
+                        );
+                    }
+
+                    process_file(&input_path, &output_path, &options)
+                })();
+
+                tx.send((input_path, result)).unwrap();
+            });
+        }
+
+        drop(tx);
+
+        let mut failures = 0usize;
+        for (path, result) in rx {
+            if let Err(err) = result {
+                error!("Failed to downmix '{}': {err:#}", path.display());
+                failures += 1;
+            }
+        }
+
+        failures
+    });
+
+    anyhow::ensure!(failures == 0, "{failures} of {file_count} file(s) failed to downmix");
+
+    Ok(())
+}
+
+/// Probes a single file's audio channels and downmixes it if it has more than two.
+fn process_file(input_path: &Path, output_path: &Path, options: &DownmixOptions) -> anyhow::Result<()> {
+    let probed = ffprobe::probe(&options.binaries.ffprobe, input_path)?;
+
+    let mut too_many_channels = false;
+    let mut channel_layout = None;
+    for stream in probed.audio_streams() {
+        if let Some(channels) = stream.channels {
+            info!("Found {channels} channels for '{}'", input_path.display());
+
+            if channels > 2 {
+                too_many_channels = true;
+                channel_layout = channel_layout.or(stream.channel_layout.as_deref());
+            }
         }
     }
 
     if too_many_channels {
         info!(
             "Downmixing '{}' to '{}'",
-            args.input_path.display(),
-            args.output_path.display()
+            input_path.display(),
+            output_path.display()
         );
 
-        downmix(args)
+        downmix(
+            input_path,
+            output_path,
+            options,
+            probed.format.duration,
+            channel_layout,
+        )
     } else {
         println!(
             "File '{}' does not need to be downmixed.",
-            args.input_path.display()
+            input_path.display()
         );
 
         Ok(())
     }
 }
 
-fn downmix(args: Args) -> anyhow::Result<()> {
-    let ffmpeg_args = [
-        "-i",
-        args.input_path.to_str().unwrap(),
-        "-hide_banner",
-        "-loglevel",
-        "error",
-        "-y",
-        "-c:v",
-        "copy",
-        "-ac",
-        "2",
-        args.output_path
-            .to_str()
-            .context(format!("invalid path '{}'", args.output_path.display()))?,
-    ];
-
-    let output = Command::new("ffmpeg").args(ffmpeg_args).output()?;
+fn downmix(
+    input_path: &Path,
+    output_path: &Path,
+    options: &DownmixOptions,
+    duration_secs: Option<f64>,
+    channel_layout: Option<&str>,
+) -> anyhow::Result<()> {
+    if matches!(options.backend, BackendKind::Symphonia) {
+        match channel_layout {
+            Some(channel_layout) => {
+                let preset = options.matrix.unwrap_or(MatrixPreset::Itu);
+                if options.matrix.is_none() {
+                    info!(
+                        "no --matrix given; the symphonia backend applies the {preset:?} preset by \
+                         default, unlike the ffmpeg backend's default plain -ac 2 mix"
+                    );
+                }
+                return backend::run_symphonia(
+                    &options.binaries.ffmpeg,
+                    input_path,
+                    output_path,
+                    preset,
+                    channel_layout,
+                    options.force,
+                    options.normalize,
+                );
+            }
+            None => warn!(
+                "'{}' has no known channel layout; falling back to the ffmpeg backend instead of the requested symphonia backend",
+                input_path.display()
+            ),
+        }
+    }
 
-    ensure!(
-        output.stderr.is_empty(),
-        "Error from ffmpeg:\n{}",
-        String::from_utf8_lossy(&output.stderr)
-    );
+    let input_str = input_path
+        .to_str()
+        .context(format!("invalid path '{}'", input_path.display()))?;
+    let output_str = output_path
+        .to_str()
+        .context(format!("invalid path '{}'", output_path.display()))?;
+
+    let mut ffmpeg_args = vec!["-i", input_str, "-hide_banner", "-loglevel", "error"];
+    if options.force {
+        ffmpeg_args.push("-y");
+    }
+    ffmpeg_args.extend(["-c:v", "copy"]);
+
+    let audio_filter = build_audio_filter(options, channel_layout);
+    if let Some(filter) = &audio_filter.af {
+        ffmpeg_args.extend(["-af", filter]);
+    }
+    if audio_filter.needs_ac2 {
+        ffmpeg_args.extend(["-ac", "2"]);
+    }
+
+    ffmpeg_args.push(output_str);
+
+    let (status, stderr) = progress::run_with_progress(
+        &options.binaries.ffmpeg,
+        &ffmpeg_args,
+        duration_secs,
+        options.quiet,
+    )?;
+
+    ensure!(status.success(), "ffmpeg exited with {status}:\n{stderr}");
 
-    info!("Successfully downmixed to '{}'", args.output_path.display());
+    info!("Successfully downmixed to '{}'", output_path.display());
 
     Ok(())
 }
+
+/// The `-af`/`-ac` options needed to apply the requested matrix preset and normalization.
+struct AudioFilter {
+    /// The `-af` filter chain to apply, if any.
+    af: Option<String>,
+    /// Whether `-ac 2` is still needed to fold to stereo. Explicit `pan` filters already
+    /// produce stereo output themselves; a bare `loudnorm` (or no filter at all) does not.
+    needs_ac2: bool,
+}
+
+/// Builds the audio options for the requested matrix preset and normalization. When no `pan`
+/// filter could be built (no preset selected, or an unrecognized `channel_layout`), `-ac 2` is
+/// always kept so the file still gets downmixed to stereo even if only normalization applies.
+fn build_audio_filter(options: &DownmixOptions, channel_layout: Option<&str>) -> AudioFilter {
+    let pan = options
+        .matrix
+        .zip(channel_layout)
+        .and_then(|(preset, layout)| matrix::pan_filter(preset, layout));
+
+    if pan.is_none() {
+        if let Some(preset) = options.matrix {
+            warn!(
+                "requested matrix preset {preset:?} has no gains for channel layout {:?}; falling back to ffmpeg's default -ac 2 mix",
+                channel_layout.unwrap_or("unknown")
+            );
+        }
+    }
+
+    let needs_ac2 = pan.is_none();
+    let af = match (pan, options.normalize) {
+        (Some(pan), true) => Some(format!("{pan},loudnorm")),
+        (Some(pan), false) => Some(pan),
+        (None, true) => Some("loudnorm".to_owned()),
+        (None, false) => None,
+    };
+
+    AudioFilter { af, needs_ac2 }
+}
@@ -0,0 +1,272 @@
+use anyhow::Context;
+use clap::ValueEnum;
+
+/// Named downmix coefficient presets, selected via `--matrix`.
+///
+/// Each preset folds a multichannel `channel_layout` down to stereo with explicit gains
+/// instead of relying on ffmpeg's default `-ac 2` mix, which does not apply the ITU-R BS.775
+/// surround/center gains these presets use. The gains are shared by both the `ffmpeg` backend
+/// (as a `pan` filter) and the `symphonia` backend (as per-sample weighted sums).
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum MatrixPreset {
+    /// Dolby Surround-style fold: ITU gains plus a small opposite-channel phase cancellation
+    /// term for extra L/R separation.
+    Dolby,
+    /// The ITU-R BS.775 reference downmix: center and surround channels folded in at -3 dB.
+    Itu,
+    /// Dolby Pro Logic II style matrix encode, cross-mixing the opposite surround channel.
+    Dpl2,
+}
+
+/// -3 dB, the ITU-R BS.775 gain applied to center and surround channels when folding to stereo.
+const SURROUND_GAIN: f64 = 0.707;
+
+/// The channels present in a layout, in standard channel-role names.
+struct Layout {
+    center: Option<&'static str>,
+    left_surround: &'static str,
+    right_surround: &'static str,
+}
+
+/// The standard channel order symphonia (and ffmpeg) decode a layout's samples in.
+///
+/// `"7.1(wide)"` is deliberately not recognized here: ffmpeg's real layout of that name
+/// replaces the last two channels with front-left/right-of-center (`FLC`/`FRC`), not side
+/// surrounds, and folding those into the surround-gain slot like `"7.1"` would silently mix
+/// front-image content into the wrong channel. Left unrecognized, it makes the ffmpeg backend
+/// fall back to its default `-ac 2` mix and the symphonia backend reject the file, instead of
+/// applying a wrong fold.
+pub fn channel_order(channel_layout: &str) -> Option<Vec<&'static str>> {
+    match channel_layout {
+        "5.1" | "5.1(side)" => Some(vec!["FL", "FR", "FC", "LFE", "BL", "BR"]),
+        "7.1" => Some(vec!["FL", "FR", "FC", "LFE", "BL", "BR", "SL", "SR"]),
+        "quad" | "quad(side)" => Some(vec!["FL", "FR", "BL", "BR"]),
+        _ => None,
+    }
+}
+
+fn layout_for(channel_layout: &str) -> Option<Layout> {
+    match channel_layout {
+        "5.1" | "5.1(side)" => Some(Layout {
+            center: Some("FC"),
+            left_surround: "BL",
+            right_surround: "BR",
+        }),
+        "7.1" => Some(Layout {
+            center: Some("FC"),
+            left_surround: "SL",
+            right_surround: "SR",
+        }),
+        "quad" | "quad(side)" => Some(Layout {
+            center: None,
+            left_surround: "BL",
+            right_surround: "BR",
+        }),
+        _ => None,
+    }
+}
+
+/// A weighted sum of named source channels producing one output channel, e.g.
+/// `[("FL", 1.0), ("FC", 0.707), ("BL", 0.707)]` for `L = FL + 0.707*FC + 0.707*BL`.
+pub type Terms = Vec<(&'static str, f64)>;
+
+/// The stereo downmix gains for a preset applied to a given source layout.
+pub struct ChannelGains {
+    pub left: Terms,
+    pub right: Terms,
+}
+
+/// Computes the per-channel gains for `preset` given a source `channel_layout` (e.g. `"5.1"`,
+/// `"7.1"`, `"quad"`, as reported by ffprobe). Returns `None` for layouts this tool doesn't
+/// know a fold for.
+pub fn gains(preset: MatrixPreset, channel_layout: &str) -> Option<ChannelGains> {
+    let layout = layout_for(channel_layout)?;
+
+    let mut left: Terms = vec![("FL", 1.0)];
+    let mut right: Terms = vec![("FR", 1.0)];
+
+    if let Some(center) = layout.center {
+        left.push((center, SURROUND_GAIN));
+        right.push((center, SURROUND_GAIN));
+    }
+
+    match preset {
+        MatrixPreset::Itu => {
+            left.push((layout.left_surround, SURROUND_GAIN));
+            right.push((layout.right_surround, SURROUND_GAIN));
+        }
+        MatrixPreset::Dolby => {
+            let cross_gain = SURROUND_GAIN * 0.5;
+            left.push((layout.left_surround, SURROUND_GAIN));
+            left.push((layout.right_surround, -cross_gain));
+            right.push((layout.right_surround, SURROUND_GAIN));
+            right.push((layout.left_surround, -cross_gain));
+        }
+        MatrixPreset::Dpl2 => {
+            // Pro Logic II phantom-rear imaging: blend a little of the opposite surround
+            // channel back in, rather than cancelling it out as the plain Dolby fold does.
+            let cross_gain = SURROUND_GAIN * SURROUND_GAIN;
+            left.push((layout.left_surround, SURROUND_GAIN));
+            left.push((layout.right_surround, cross_gain));
+            right.push((layout.right_surround, SURROUND_GAIN));
+            right.push((layout.left_surround, cross_gain));
+        }
+    }
+
+    Some(ChannelGains { left, right })
+}
+
+/// Builds the `pan=stereo|...` filter string for `preset` given a source `channel_layout`.
+/// Returns `None` for layouts this tool doesn't know a fold for, so the caller can fall back
+/// to ffmpeg's default `-ac 2`.
+pub fn pan_filter(preset: MatrixPreset, channel_layout: &str) -> Option<String> {
+    let gains = gains(preset, channel_layout)?;
+
+    Some(format!(
+        "pan=stereo|c0={}|c1={}",
+        format_terms(&gains.left),
+        format_terms(&gains.right)
+    ))
+}
+
+/// Applies a preset's gains to deinterleaved source `channels` (one sample buffer per channel,
+/// in the order [`channel_order`] returns for `channel_layout`), producing interleaved stereo
+/// samples (`[L0, R0, L1, R1, ...]`).
+pub fn downmix_to_stereo(
+    preset: MatrixPreset,
+    channel_layout: &str,
+    channels: &[Vec<f32>],
+) -> anyhow::Result<Vec<f32>> {
+    let gains = gains(preset, channel_layout)
+        .with_context(|| format!("no downmix matrix for layout '{channel_layout}'"))?;
+    let order = channel_order(channel_layout).expect("gains() succeeded for a known layout");
+
+    anyhow::ensure!(
+        channels.len() == order.len(),
+        "expected {} channels for layout '{channel_layout}', got {}",
+        order.len(),
+        channels.len()
+    );
+
+    let frames = channels.iter().map(|channel| channel.len()).min().unwrap_or(0);
+    let mut interleaved = Vec::with_capacity(frames * 2);
+
+    for frame in 0..frames {
+        let sample_of = |role: &str| -> f32 {
+            order
+                .iter()
+                .position(|candidate| *candidate == role)
+                .map_or(0.0, |index| channels[index][frame])
+        };
+
+        interleaved.push(mix(&gains.left, sample_of));
+        interleaved.push(mix(&gains.right, sample_of));
+    }
+
+    Ok(interleaved)
+}
+
+fn mix(terms: &Terms, sample_of: impl Fn(&str) -> f32) -> f32 {
+    terms
+        .iter()
+        .map(|(role, gain)| *gain as f32 * sample_of(role))
+        .sum()
+}
+
+fn format_terms(terms: &Terms) -> String {
+    terms
+        .iter()
+        .enumerate()
+        .map(|(i, (name, gain))| {
+            let gain = round_gain(*gain);
+            match (i, gain.is_sign_negative()) {
+                (0, _) => name.to_string(),
+                (_, false) => format!("+{gain}*{name}"),
+                (_, true) => format!("{gain}*{name}"),
+            }
+        })
+        .collect()
+}
+
+/// Rounds a gain to 6 decimal places before it's formatted into a `pan` filter string, so
+/// products of gains like `SURROUND_GAIN * SURROUND_GAIN` don't print f64 rounding noise
+/// (e.g. `0.49984899999999993` instead of `0.499849`).
+fn round_gain(gain: f64) -> f64 {
+    (gain * 1_000_000.0).round() / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One frame of silence on every channel except `channel`, which gets `value`.
+    fn single_channel_frame(channel_count: usize, channel: usize, value: f32) -> Vec<Vec<f32>> {
+        (0..channel_count)
+            .map(|i| vec![if i == channel { value } else { 0.0 }])
+            .collect()
+    }
+
+    #[test]
+    fn itu_downmix_folds_center_at_surround_gain() {
+        // 5.1 order: FL, FR, FC, LFE, BL, BR
+        let channels = single_channel_frame(6, 2, 1.0); // FC = 1.0, everything else silent
+        let stereo = downmix_to_stereo(MatrixPreset::Itu, "5.1", &channels).unwrap();
+
+        assert_eq!(stereo.len(), 2);
+        assert!((stereo[0] - SURROUND_GAIN as f32).abs() < 1e-6);
+        assert!((stereo[1] - SURROUND_GAIN as f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn itu_downmix_passes_front_left_right_through_unattenuated() {
+        let mut channels = single_channel_frame(6, 0, 1.0); // FL = 1.0
+        channels[1][0] = 0.5; // FR = 0.5
+        let stereo = downmix_to_stereo(MatrixPreset::Itu, "5.1", &channels).unwrap();
+
+        assert!((stereo[0] - 1.0).abs() < 1e-6);
+        assert!((stereo[1] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn dolby_downmix_cancels_some_of_the_opposite_back_channel() {
+        // BL = 1.0 should bleed a small negative term into the right channel.
+        let channels = single_channel_frame(6, 4, 1.0);
+        let stereo = downmix_to_stereo(MatrixPreset::Dolby, "5.1", &channels).unwrap();
+
+        assert!((stereo[0] - SURROUND_GAIN as f32).abs() < 1e-6);
+        assert!(stereo[1] < 0.0);
+    }
+
+    #[test]
+    fn quad_has_no_center_term() {
+        let gains = gains(MatrixPreset::Itu, "quad").unwrap();
+        assert!(!gains.left.iter().any(|(name, _)| *name == "FC"));
+        assert!(!gains.right.iter().any(|(name, _)| *name == "FC"));
+    }
+
+    #[test]
+    fn unknown_layout_has_no_matrix() {
+        assert!(gains(MatrixPreset::Itu, "7.1(wide)").is_none());
+        assert!(pan_filter(MatrixPreset::Itu, "mono").is_none());
+    }
+
+    #[test]
+    fn downmix_to_stereo_rejects_channel_count_mismatch() {
+        let channels = single_channel_frame(4, 0, 1.0); // 5.1 needs 6 channels, not 4
+        assert!(downmix_to_stereo(MatrixPreset::Itu, "5.1", &channels).is_err());
+    }
+
+    #[test]
+    fn pan_filter_leads_each_side_with_a_bare_channel_name() {
+        let filter = pan_filter(MatrixPreset::Itu, "5.1").unwrap();
+        assert!(filter.starts_with("pan=stereo|c0=FL+"));
+        assert!(filter.contains("|c1=FR+"));
+    }
+
+    #[test]
+    fn dpl2_pan_filter_has_no_floating_point_rounding_noise() {
+        let filter = pan_filter(MatrixPreset::Dpl2, "5.1").unwrap();
+        assert!(!filter.contains("0.49984899999999993"));
+        assert!(filter.contains("0.499849"));
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    thread,
+};
+
+/// Runs `ffmpeg_bin` with the given arguments, rendering a live progress bar parsed from its
+/// `-progress pipe:1` stream. The bar is suppressed when `quiet` is set or `duration_secs`
+/// is unknown. Returns the process's exit status and captured stderr.
+pub fn run_with_progress(
+    ffmpeg_bin: &Path,
+    ffmpeg_args: &[&str],
+    duration_secs: Option<f64>,
+    quiet: bool,
+) -> anyhow::Result<(ExitStatus, String)> {
+    let mut child = Command::new(ffmpeg_bin)
+        .args(ffmpeg_args)
+        .args(["-progress", "pipe:1", "-nostats"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .expect("child spawned with piped stdout");
+    let stderr = child
+        .stderr
+        .take()
+        .expect("child spawned with piped stderr");
+
+    // Drain stderr on its own thread: ffmpeg can write enough of it to fill the OS pipe
+    // buffer while this thread is still blocked reading the -progress lines on stdout, and
+    // vice versa, which would otherwise deadlock the two processes against each other.
+    let stderr_thread = thread::spawn(move || {
+        let mut stderr = stderr;
+        let mut buf = String::new();
+        stderr.read_to_string(&mut buf).map(|_| buf)
+    });
+
+    let duration_us = duration_secs.map(|secs| secs * 1_000_000.0);
+    let mut state = ProgressState::default();
+
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        if let Some(event) = state.handle_line(&line, duration_us) {
+            render_bar(event.fraction, quiet);
+
+            if event.is_end {
+                render_bar(1.0, quiet);
+                if !quiet {
+                    eprintln!();
+                }
+            }
+        }
+    }
+
+    let stderr = stderr_thread
+        .join()
+        .map_err(|_| anyhow::anyhow!("stderr reader thread panicked"))??;
+
+    Ok((child.wait()?, stderr))
+}
+
+/// A render-worthy event parsed from one line of ffmpeg's `-progress` stream.
+struct ProgressEvent {
+    /// The fraction of `duration_secs` completed so far.
+    fraction: f64,
+    /// Whether this is the stream's final `progress=end` line.
+    is_end: bool,
+}
+
+/// Tracks the running `out_time_us=` total from an ffmpeg `-progress` stream, split out from
+/// [`run_with_progress`] so the key=value parsing and fraction math can be tested without
+/// spawning a process, the way `ffprobe::parse` and `matrix::gains` are.
+#[derive(Default)]
+struct ProgressState {
+    out_time_us: f64,
+}
+
+impl ProgressState {
+    /// Parses one `key=value` line, returning a [`ProgressEvent`] when the line is a
+    /// `progress=` line and `duration_us` is known. Unrecognized keys and `out_time_us=`
+    /// lines update internal state but produce no event of their own.
+    fn handle_line(&mut self, line: &str, duration_us: Option<f64>) -> Option<ProgressEvent> {
+        let (key, value) = line.split_once('=')?;
+
+        match key {
+            "out_time_us" => {
+                self.out_time_us = value.parse().unwrap_or(self.out_time_us);
+                None
+            }
+            "progress" => {
+                let duration_us = duration_us?;
+                let fraction = (self.out_time_us / duration_us).clamp(0.0, 1.0);
+                Some(ProgressEvent {
+                    fraction,
+                    is_end: value == "end",
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Renders a `[####    ] NN%` progress bar on stderr, overwriting the previous line.
+fn render_bar(fraction: f64, quiet: bool) {
+    if quiet {
+        return;
+    }
+
+    const WIDTH: usize = 30;
+    let filled = (fraction * WIDTH as f64).round() as usize;
+
+    eprint!(
+        "\r[{}{}] {:>3}%",
+        "#".repeat(filled),
+        " ".repeat(WIDTH - filled),
+        (fraction * 100.0).round() as u32,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_time_us_updates_state_without_an_event() {
+        let mut state = ProgressState::default();
+        assert!(state.handle_line("out_time_us=5000000", Some(10_000_000.0)).is_none());
+        assert_eq!(state.out_time_us, 5_000_000.0);
+    }
+
+    #[test]
+    fn progress_line_is_ignored_without_a_known_duration() {
+        let mut state = ProgressState::default();
+        state.handle_line("out_time_us=5000000", None);
+        assert!(state.handle_line("progress=continue", None).is_none());
+    }
+
+    #[test]
+    fn progress_line_reports_the_fraction_of_duration_elapsed() {
+        let mut state = ProgressState::default();
+        state.handle_line("out_time_us=2500000", Some(10_000_000.0));
+        let event = state.handle_line("progress=continue", Some(10_000_000.0)).unwrap();
+
+        assert!((event.fraction - 0.25).abs() < 1e-9);
+        assert!(!event.is_end);
+    }
+
+    #[test]
+    fn progress_end_is_flagged_and_fraction_is_clamped() {
+        let mut state = ProgressState::default();
+        state.handle_line("out_time_us=99000000", Some(10_000_000.0)); // past the known duration
+        let event = state.handle_line("progress=end", Some(10_000_000.0)).unwrap();
+
+        assert_eq!(event.fraction, 1.0);
+        assert!(event.is_end);
+    }
+
+    #[test]
+    fn lines_without_an_equals_sign_are_ignored() {
+        let mut state = ProgressState::default();
+        assert!(state.handle_line("bogus line", Some(10_000_000.0)).is_none());
+    }
+}